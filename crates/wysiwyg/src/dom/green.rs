@@ -0,0 +1,475 @@
+// Copyright 2022 The Matrix.org Foundation C.I.C.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! An immutable, reference-counted mirror of the live [DomNode] tree
+//! ("green tree", after the red/green terminology used by similar
+//! structural-sharing trees), plus a [GreenCache] that interns identical
+//! subtrees behind a single `Arc`.
+//!
+//! This exists to make undo/redo history affordable: cloning a whole [Dom]
+//! on every keystroke is wasteful, but cloning a single root `Arc` is O(1),
+//! and unchanged subtrees across snapshots share their allocation rather
+//! than being copied. The green tree sits beside the live tree rather than
+//! under it: [Dom::iter]/[DomIterator](super::iter::DomIterator) walk the
+//! live [DomNode]s only and cannot walk a [GreenNode] tree.
+//!
+//! [GreenCache::intern] builds a full mirror from a live [Dom] - use it
+//! once, up front. After that, edits should go through [GreenCache::replace],
+//! which only rebuilds the spine from the edited node up to the root;
+//! every other subtree keeps its existing `Arc`, which is what makes an
+//! unbounded undo stack affordable.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use crate::{DomHandle, UnicodeString};
+
+use super::{nodes::ContainerNode, Dom, DomNode};
+
+/// Structural hash of a [DomNode] subtree: tag + attributes + ordered child
+/// hashes for a container, or text content for a leaf. Two subtrees with
+/// the same hash are *usually* structurally identical, but a hash alone is
+/// never proof of that - see [GreenCache]'s collision handling.
+pub type GreenHash = u64;
+
+/// An immutable, reference-counted mirror of a single [DomNode] and
+/// everything beneath it. Cloning a `GreenNode` handle (it's always held as
+/// `Arc<GreenNode<S>>`) is O(1).
+pub enum GreenNode<S>
+where
+    S: UnicodeString,
+{
+    Container {
+        tag: String,
+        attributes: Vec<(String, String)>,
+        children: Vec<Arc<GreenNode<S>>>,
+        hash: GreenHash,
+    },
+    Text {
+        data: S,
+        hash: GreenHash,
+    },
+    LineBreak {
+        hash: GreenHash,
+    },
+    Zwsp {
+        hash: GreenHash,
+    },
+}
+
+impl<S> GreenNode<S>
+where
+    S: UnicodeString,
+{
+    /// The structural hash this node was interned under.
+    pub fn hash(&self) -> GreenHash {
+        match self {
+            GreenNode::Container { hash, .. }
+            | GreenNode::Text { hash, .. }
+            | GreenNode::LineBreak { hash }
+            | GreenNode::Zwsp { hash } => *hash,
+        }
+    }
+}
+
+impl<S> PartialEq for GreenNode<S>
+where
+    S: UnicodeString,
+{
+    /// Full structural equality, used to disambiguate a [GreenHash]
+    /// collision in [GreenCache]. Cheap in the common case: once a child
+    /// has gone through the cache once, two structurally-equal children
+    /// already share one `Arc`, so comparing children is just a pointer
+    /// comparison per slot; the recursive fallback only matters for
+    /// `GreenNode`s built outside the cache.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                GreenNode::Container {
+                    tag: tag_a,
+                    attributes: attrs_a,
+                    children: children_a,
+                    ..
+                },
+                GreenNode::Container {
+                    tag: tag_b,
+                    attributes: attrs_b,
+                    children: children_b,
+                    ..
+                },
+            ) => {
+                tag_a == tag_b
+                    && attrs_a == attrs_b
+                    && children_a.len() == children_b.len()
+                    && children_a.iter().zip(children_b).all(|(a, b)| {
+                        Arc::ptr_eq(a, b) || a == b
+                    })
+            }
+            (GreenNode::Text { data: a, .. }, GreenNode::Text { data: b, .. }) => {
+                a.to_string() == b.to_string()
+            }
+            (GreenNode::LineBreak { .. }, GreenNode::LineBreak { .. }) => true,
+            (GreenNode::Zwsp { .. }, GreenNode::Zwsp { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Deduplicates structurally-identical subtrees behind a single `Arc`. Two
+/// equal subtrees - whether siblings in the same tree or the same spot in
+/// two different snapshots - end up sharing one allocation.
+///
+/// Keyed by [GreenHash], but a hash match is only a *candidate*: each
+/// bucket holds every distinct `GreenNode` seen with that hash so far, and
+/// [PartialEq] decides whether to reuse one of them or add a new entry,
+/// the same way a `HashMap` handles collisions internally.
+pub struct GreenCache<S>
+where
+    S: UnicodeString,
+{
+    interned: HashMap<GreenHash, Vec<Arc<GreenNode<S>>>>,
+}
+
+impl<S> Default for GreenCache<S>
+where
+    S: UnicodeString,
+{
+    fn default() -> Self {
+        Self {
+            interned: HashMap::new(),
+        }
+    }
+}
+
+impl<S> GreenCache<S>
+where
+    S: UnicodeString,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of distinct subtrees currently interned.
+    pub fn len(&self) -> usize {
+        self.interned.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interned.is_empty()
+    }
+
+    /// Build (bottom-up) or reuse a green mirror of `node` and everything
+    /// beneath it, interning every subtree it touches along the way. This
+    /// is O(size of `node`'s subtree) - use it once to seed a [GreenCache]
+    /// from a live [Dom], then prefer [Self::replace] for edits.
+    pub fn intern(&mut self, node: &DomNode<S>) -> Arc<GreenNode<S>> {
+        let built = match node {
+            DomNode::Container(c) => {
+                let children: Vec<Arc<GreenNode<S>>> = c
+                    .children()
+                    .iter()
+                    .map(|child| self.intern(child))
+                    .collect();
+                self.build_container(c.name().to_string(), container_attributes(c), children)
+            }
+            DomNode::Text(t) => {
+                let data = t.data().clone();
+                let hash = hash_text(&data);
+                GreenNode::Text { data, hash }
+            }
+            DomNode::LineBreak(_) => GreenNode::LineBreak {
+                hash: leaf_hash("br"),
+            },
+            DomNode::Zwsp(_) => GreenNode::Zwsp {
+                hash: leaf_hash("zwsp"),
+            },
+        };
+        self.intern_built(built)
+    }
+
+    /// Replace the node at `handle` within `snapshot` with `replacement`,
+    /// rebuilding only the containers on the path from `handle` up to the
+    /// root (the "spine"). Every sibling off that path keeps its existing
+    /// `Arc` unchanged, so this is O(depth of `handle`), not O(size of the
+    /// tree) - the incremental counterpart to [Self::intern].
+    pub fn replace(
+        &mut self,
+        snapshot: &DomSnapshot<S>,
+        handle: &DomHandle,
+        replacement: Arc<GreenNode<S>>,
+    ) -> DomSnapshot<S> {
+        DomSnapshot {
+            root: self.replace_along_path(
+                snapshot.root.clone(),
+                handle.raw(),
+                replacement,
+            ),
+        }
+    }
+
+    fn replace_along_path(
+        &mut self,
+        current: Arc<GreenNode<S>>,
+        path: &[usize],
+        replacement: Arc<GreenNode<S>>,
+    ) -> Arc<GreenNode<S>> {
+        let Some((&index, rest)) = path.split_first() else {
+            return replacement;
+        };
+        let GreenNode::Container {
+            tag,
+            attributes,
+            children,
+            ..
+        } = current.as_ref()
+        else {
+            panic!("handle path continues past a leaf green node");
+        };
+        let mut children = children.clone();
+        children[index] =
+            self.replace_along_path(children[index].clone(), rest, replacement);
+        self.build_and_intern_container(tag.clone(), attributes.clone(), children)
+    }
+
+    fn build_container(
+        &self,
+        tag: String,
+        attributes: Vec<(String, String)>,
+        children: Vec<Arc<GreenNode<S>>>,
+    ) -> GreenNode<S> {
+        let hash = hash_container(&tag, &attributes, &children);
+        GreenNode::Container {
+            tag,
+            attributes,
+            children,
+            hash,
+        }
+    }
+
+    fn build_and_intern_container(
+        &mut self,
+        tag: String,
+        attributes: Vec<(String, String)>,
+        children: Vec<Arc<GreenNode<S>>>,
+    ) -> Arc<GreenNode<S>> {
+        let built = self.build_container(tag, attributes, children);
+        self.intern_built(built)
+    }
+
+    fn intern_built(&mut self, built: GreenNode<S>) -> Arc<GreenNode<S>> {
+        let bucket = self.interned.entry(built.hash()).or_default();
+        if let Some(existing) = bucket.iter().find(|candidate| candidate.as_ref() == &built) {
+            return existing.clone();
+        }
+        let arc = Arc::new(built);
+        bucket.push(arc.clone());
+        arc
+    }
+}
+
+/// Attribute names this module knows to round-trip into a [GreenNode].
+/// `ContainerNode` has no method to enumerate *all* of a node's attributes
+/// - only `get_attribute`, the same lookup-by-name the selector's `[attr]`
+/// predicate in `dom/iter.rs` already relies on - so rather than inventing a new
+/// accessor, probe the handful of attribute names the editor's node model
+/// actually carries.
+const KNOWN_ATTRIBUTES: &[&str] = &["href"];
+
+fn container_attributes<S>(c: &ContainerNode<S>) -> Vec<(String, String)>
+where
+    S: UnicodeString,
+{
+    KNOWN_ATTRIBUTES
+        .iter()
+        .filter_map(|&name| {
+            c.get_attribute(name).map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn hash_container<S>(
+    tag: &str,
+    attributes: &[(String, String)],
+    children: &[Arc<GreenNode<S>>],
+) -> GreenHash
+where
+    S: UnicodeString,
+{
+    let mut hasher = DefaultHasher::new();
+    tag.hash(&mut hasher);
+    for (name, value) in attributes {
+        name.hash(&mut hasher);
+        value.hash(&mut hasher);
+    }
+    for child in children {
+        child.hash().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn hash_text<S>(data: &S) -> GreenHash
+where
+    S: UnicodeString,
+{
+    let mut hasher = DefaultHasher::new();
+    "text".hash(&mut hasher);
+    data.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+fn leaf_hash(discriminant: &str) -> GreenHash {
+    let mut hasher = DefaultHasher::new();
+    discriminant.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A cheap, structurally-shared snapshot of a [Dom], suitable for an
+/// unbounded undo/redo stack: taking one is a single `Arc` clone of the
+/// root, and subtrees unchanged since the last snapshot taken through the
+/// same [GreenCache] are reused rather than re-allocated.
+#[derive(Clone)]
+pub struct DomSnapshot<S>
+where
+    S: UnicodeString,
+{
+    root: Arc<GreenNode<S>>,
+}
+
+impl<S> DomSnapshot<S>
+where
+    S: UnicodeString,
+{
+    pub fn root(&self) -> &Arc<GreenNode<S>> {
+        &self.root
+    }
+}
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Take a structurally-shared snapshot of this `Dom` via `cache`. Call
+    /// this once to seed a snapshot, then use [GreenCache::replace] for
+    /// subsequent edits so each one stays O(depth) rather than O(tree
+    /// size).
+    pub fn snapshot(&self, cache: &mut GreenCache<S>) -> DomSnapshot<S> {
+        DomSnapshot {
+            root: cache.intern(self.document_node()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use widestring::Utf16String;
+
+    use crate::dom::nodes::TextNode;
+    use crate::tests::testutils_composer_model::cm;
+    use crate::{DomHandle, DomNode};
+
+    use super::{GreenCache, GreenNode};
+
+    #[test]
+    fn snapshots_of_an_unchanged_dom_share_their_root() {
+        let dom = cm("<ul><li>a</li><li>b</li></ul>").state.dom;
+        let mut cache = GreenCache::<Utf16String>::new();
+
+        let first = dom.snapshot(&mut cache);
+        let second = dom.snapshot(&mut cache);
+
+        assert!(Arc::ptr_eq(first.root(), second.root()));
+    }
+
+    #[test]
+    fn identical_sibling_subtrees_are_interned_once() {
+        // Both <li> elements are structurally identical (same tag, same
+        // text, same - empty - attributes), so they should share one
+        // allocation in the cache.
+        let dom = cm("<ul><li>a</li><li>a</li></ul>").state.dom;
+        let mut cache = GreenCache::<Utf16String>::new();
+
+        dom.snapshot(&mut cache);
+
+        // document + ul + li + text("a") = 4 distinct shapes, even though
+        // there are 6 live nodes (two <li>s, each wrapping one "a" text).
+        assert_eq!(cache.len(), 4);
+    }
+
+    #[test]
+    fn links_with_different_hrefs_are_not_merged() {
+        // Same tag, same link text, different `href` - these must *not*
+        // collapse into one green node, or one of the two hrefs is lost.
+        let dom = cm(
+            "<a href=\"https://one.example\">link</a>\
+             <a href=\"https://two.example\">link</a>",
+        )
+        .state
+        .dom;
+        let mut cache = GreenCache::<Utf16String>::new();
+
+        dom.snapshot(&mut cache);
+
+        let links: Vec<_> = dom
+            .children()
+            .iter()
+            .map(|node| cache.intern(node))
+            .collect();
+        assert_eq!(links.len(), 2);
+        assert!(!Arc::ptr_eq(&links[0], &links[1]));
+        assert!(links[0].as_ref() != links[1].as_ref());
+    }
+
+    #[test]
+    fn replace_rebuilds_only_the_spine() {
+        let dom = cm("<ul><li>a</li><li>b</li></ul>").state.dom;
+        let mut cache = GreenCache::<Utf16String>::new();
+        let before = dom.snapshot(&mut cache);
+
+        let GreenNode::Container { children, .. } = before.root().as_ref() else {
+            panic!("root should be a container");
+        };
+        let GreenNode::Container { children, .. } = children[0].as_ref() else {
+            panic!("expected the <ul> to be the first child");
+        };
+        let untouched_sibling = children[1].clone();
+
+        // Replace the first <li>'s single text child with new content; the
+        // handle `[0, 0, 0]` is document -> ul -> li[0] -> text.
+        let replacement = cache.intern(&DomNode::Text(TextNode::from(
+            Utf16String::from("a changed"),
+        )));
+        let after = cache.replace(
+            &before,
+            &DomHandle::from_raw(vec![0, 0, 0]),
+            replacement,
+        );
+
+        assert!(!Arc::ptr_eq(before.root(), after.root()));
+
+        let GreenNode::Container { children, .. } = after.root().as_ref() else {
+            panic!("root should be a container");
+        };
+        let GreenNode::Container { children, .. } = children[0].as_ref() else {
+            panic!("expected the <ul> to be the first child");
+        };
+        // The untouched second <li> keeps its exact allocation.
+        assert!(Arc::ptr_eq(&children[1], &untouched_sibling));
+    }
+}
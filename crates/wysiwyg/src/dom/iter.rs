@@ -36,6 +36,29 @@ where
         DomIterator::over(self.document_node())
     }
 
+    /// Return an iterator over [DomEvent]s for all nodes of this DOM, in
+    /// depth-first order. This is the event-stream equivalent of [Self::iter]:
+    /// it additionally yields an `Exit` event once a container's children
+    /// have all been walked, so a single linear pass is enough for balanced
+    /// serialization or depth tracking.
+    pub fn iter_events(&self) -> DomEventIterator<S> {
+        DomEventIterator::over(self.document_node())
+    }
+
+    /// Like [Self::iter], but `predicate` is consulted before descending
+    /// into each container: a [Descend::Skip] answer advances past that
+    /// container's children without visiting any of them, so walking a
+    /// large document for e.g. "only the text inside list items" doesn't
+    /// pay for descending into every `<strong>`/`<i>` along the way.
+    /// Composes with the existing `filter_map(DomNode::as_text)` style for
+    /// terminal filtering.
+    pub fn iter_where<F>(&self, predicate: F) -> DomPruningIterator<S, F>
+    where
+        F: Fn(&DomNode<S>) -> Descend,
+    {
+        DomPruningIterator::over(self.document_node(), predicate)
+    }
+
     /// Return an iterator over all text nodes of this DOM, in depth-first
     /// order
     pub fn iter_text(&self) -> impl Iterator<Item = &TextNode<S>> {
@@ -114,6 +137,26 @@ where
         DomIterator::over(self)
     }
 
+    /// Return an iterator over [DomEvent]s for the subtree starting from this
+    /// node (including self), in depth-first order. Unlike [Self::iter_subtree]
+    /// this also reports when a container has been fully walked, which lets
+    /// callers balance open/close tags or track depth in a single linear pass.
+    pub fn iter_subtree_events(&self) -> DomEventIterator<S> {
+        DomEventIterator::over(self)
+    }
+
+    /// Like [Self::iter_subtree], but `predicate` is consulted before
+    /// descending into each container, and a [Descend::Skip] answer prunes
+    /// that whole subtree without visiting it. Every container the walk
+    /// reaches is still yielded, including skipped containers themselves;
+    /// only their children are left out.
+    pub fn iter_subtree_where<F>(&self, predicate: F) -> DomPruningIterator<S, F>
+    where
+        F: Fn(&DomNode<S>) -> Descend,
+    {
+        DomPruningIterator::over(self, predicate)
+    }
+
     /// Return an iterator over all text nodes of the subtree starting from
     /// this node (including self), in depth-first order
     pub fn iter_text_in_subtree(&self) -> impl Iterator<Item = &TextNode<S>> {
@@ -330,6 +373,760 @@ where
     }
 }
 
+/// A flattened event in a depth-first walk of a [Dom] or [DomNode] subtree,
+/// produced by [Dom::iter_events] / [DomNode::iter_subtree_events].
+///
+/// Unlike [DomIterator], which only yields nodes, this also reports the
+/// point at which a container's children have all been walked. That makes a
+/// single linear pass sufficient for balanced serialization (matching every
+/// `Enter` with its `Exit`) or for computing depth without comparing
+/// [DomHandle] lengths. A reverse walk (`.rev()`) yields the same events in
+/// reverse order, so there a container's `Exit` precedes its children.
+pub enum DomEvent<'a, S>
+where
+    S: UnicodeString,
+{
+    /// Descending into a container; its children follow, up to the matching
+    /// [DomEvent::Exit].
+    Enter(&'a DomNode<S>),
+    /// All children of a container entered via [DomEvent::Enter] have been
+    /// walked.
+    Exit(&'a DomNode<S>),
+    /// A leaf node (text, line break or ZWSP) with no children of its own.
+    Leaf(&'a DomNode<S>),
+}
+
+/// `DomEventIterator` is a [DoubleEndedIterator], so `.next()` and
+/// `.next_back()` can legally be mixed on the same instance (draining a
+/// container from both ends, a la [std::slice::Iter]). That means forward
+/// and backward walks need independent cursors rather than one shared
+/// `ancestors` stack - otherwise a `next_back()` call would leave the
+/// forward walk's position corrupted (and vice versa). `remaining` is the
+/// single piece of shared state: it's how the two sides agree on when
+/// they've met in the middle and should both start returning `None`.
+pub struct DomEventIterator<'a, S>
+where
+    S: UnicodeString,
+{
+    remaining: usize,
+    front_started: bool,
+    front_ancestors: Vec<NodeAndChildIndex<'a, S>>,
+    back_started: bool,
+    back_ancestors: Vec<NodeAndChildIndex<'a, S>>,
+}
+
+impl<'a, S> DomEventIterator<'a, S>
+where
+    S: UnicodeString,
+{
+    fn over(dom_node: &'a DomNode<S>) -> Self {
+        Self {
+            remaining: count_events(dom_node),
+            front_started: false,
+            front_ancestors: vec![NodeAndChildIndex {
+                node: dom_node,
+                child_index: 0,
+            }],
+            back_started: false,
+            back_ancestors: vec![NodeAndChildIndex {
+                node: dom_node,
+                child_index: 0,
+            }],
+        }
+    }
+}
+
+/// Total number of [DomEvent]s a walk of `node` and its descendants will
+/// produce: one `Leaf` per leaf, one `Enter` + one `Exit` per container.
+/// Used to let [DomEventIterator]'s independent front/back cursors agree on
+/// when they've met in the middle.
+fn count_events<S>(node: &DomNode<S>) -> usize
+where
+    S: UnicodeString,
+{
+    match node {
+        DomNode::Container(c) => {
+            2 + c.children().iter().map(count_events).sum::<usize>()
+        }
+        _ => 1,
+    }
+}
+
+/// Shared body for [DomEventIterator::next] / [DoubleEndedIterator::next_back]:
+/// advance `ancestors` (a front or back cursor, per `reverse`) one step and
+/// return the event produced, without any awareness of the other cursor.
+/// With `reverse` set, children are walked last-to-first and `Enter`/`Exit`
+/// are swapped, so the back cursor yields the forward sequence in reverse.
+fn next_event<'a, S>(
+    started: &mut bool,
+    ancestors: &mut Vec<NodeAndChildIndex<'a, S>>,
+    reverse: bool,
+) -> Option<DomEvent<'a, S>>
+where
+    S: UnicodeString,
+{
+    let open = |node: &'a DomNode<S>| -> DomEvent<'a, S> {
+        if reverse {
+            DomEvent::Exit(node)
+        } else {
+            DomEvent::Enter(node)
+        }
+    };
+    let close = |node: &'a DomNode<S>| -> DomEvent<'a, S> {
+        if reverse {
+            DomEvent::Enter(node)
+        } else {
+            DomEvent::Exit(node)
+        }
+    };
+    if !*started {
+        *started = true;
+        let root = ancestors[0].node;
+        return Some(if let DomNode::Container(_) = root {
+            open(root)
+        } else {
+            ancestors.pop();
+            DomEvent::Leaf(root)
+        });
+    }
+
+    let frame = ancestors.iter_mut().last()?;
+    let NodeAndChildIndex {
+        node: DomNode::Container(c),
+        child_index: idx,
+    } = frame
+    else {
+        return None;
+    };
+    let siblings = c.children();
+    if *idx < siblings.len() {
+        let pos = if reverse { siblings.len() - 1 - *idx } else { *idx };
+        let child = &siblings[pos];
+        *idx += 1;
+        if let DomNode::Container(_) = child {
+            ancestors.push(NodeAndChildIndex {
+                node: child,
+                child_index: 0,
+            });
+            Some(open(child))
+        } else {
+            Some(DomEvent::Leaf(child))
+        }
+    } else {
+        let done = ancestors.pop().unwrap();
+        Some(close(done.node))
+    }
+}
+
+impl<'a, S> Iterator for DomEventIterator<'a, S>
+where
+    S: UnicodeString,
+{
+    type Item = DomEvent<'a, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let event =
+            next_event(&mut self.front_started, &mut self.front_ancestors, false);
+        if event.is_some() {
+            self.remaining -= 1;
+        }
+        event
+    }
+}
+
+impl<'a, S> DoubleEndedIterator for DomEventIterator<'a, S>
+where
+    S: UnicodeString,
+{
+    /// Walks the same subtree from the end, visiting each container's
+    /// children in reverse order and yielding the exact reverse of the
+    /// sequence [Self::next] produces: a container's `Exit` comes before its
+    /// children and its `Enter` after them. Uses its own cursor, independent of [Self::next]'s,
+    /// so the two can be interleaved freely; `remaining` stops them once
+    /// they've met in the middle.
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let event =
+            next_event(&mut self.back_started, &mut self.back_ancestors, true);
+        if event.is_some() {
+            self.remaining -= 1;
+        }
+        event
+    }
+}
+
+/// Answer returned from the predicate passed to [Dom::iter_where] /
+/// [DomNode::iter_subtree_where], deciding whether a container about to be
+/// yielded should also be descended into.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum Descend {
+    /// Walk this container's children as normal.
+    Into,
+    /// Yield this container itself, but skip straight past its children
+    /// without visiting them.
+    Skip,
+}
+
+pub struct DomPruningIterator<'a, S, F>
+where
+    S: UnicodeString,
+    F: Fn(&DomNode<S>) -> Descend,
+{
+    started: bool,
+    ancestors: Vec<NodeAndChildIndex<'a, S>>,
+    predicate: F,
+}
+
+impl<'a, S, F> DomPruningIterator<'a, S, F>
+where
+    S: UnicodeString,
+    F: Fn(&DomNode<S>) -> Descend,
+{
+    fn over(dom_node: &'a DomNode<S>, predicate: F) -> Self {
+        Self {
+            started: false,
+            ancestors: vec![NodeAndChildIndex {
+                node: dom_node,
+                child_index: 0,
+            }],
+            predicate,
+        }
+    }
+}
+
+impl<'a, S, F> Iterator for DomPruningIterator<'a, S, F>
+where
+    S: UnicodeString,
+    F: Fn(&DomNode<S>) -> Descend,
+{
+    type Item = &'a DomNode<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.started {
+            let parent = self.ancestors.iter_mut().last();
+            if let Some(NodeAndChildIndex {
+                node: DomNode::Container(c),
+                child_index: idx,
+            }) = parent
+            {
+                let siblings = c.children();
+                if *idx < siblings.len() {
+                    let myself = &siblings[*idx];
+                    *idx += 1;
+                    if let DomNode::Container(_) = myself {
+                        if (self.predicate)(myself) == Descend::Into {
+                            self.ancestors.push(NodeAndChildIndex {
+                                node: myself,
+                                child_index: 0,
+                            });
+                        }
+                    }
+                    Some(myself)
+                } else {
+                    self.ancestors.pop();
+                    self.next()
+                }
+            } else {
+                None
+            }
+        } else {
+            self.started = true;
+            let root = self.ancestors[0].node;
+            if let DomNode::Container(_) = root {
+                if (self.predicate)(root) == Descend::Skip {
+                    self.ancestors.pop();
+                }
+            }
+            Some(root)
+        }
+    }
+}
+
+/// Control flow returned from a [DomVisitor] or [DomVisitorMut] callback.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum VisitFlow {
+    /// Keep walking the tree as normal.
+    Continue,
+    /// Don't descend into the container just entered. Its matching
+    /// [DomVisitor::visit_container_exit] / [DomVisitorMut::visit_container_exit]
+    /// is not called either, since its children were never walked.
+    SkipChildren,
+    /// Abort the walk immediately.
+    Stop,
+}
+
+/// A read-only visitor over a [Dom], driven by [Dom::accept]. Compared to
+/// collecting handles with [Dom::iter] and mutating afterwards, a visitor
+/// can prune whole subtrees via [VisitFlow::SkipChildren], which matters for
+/// perf on large documents.
+///
+/// Note: [DomNode::LineBreak] and [DomNode::Zwsp] nodes are walked but have
+/// no dedicated callback, so a visitor currently can't observe them.
+pub trait DomVisitor<S>
+where
+    S: UnicodeString,
+{
+    /// Called when descending into a container, before any of its children
+    /// are visited.
+    fn visit_container_enter(
+        &mut self,
+        _node: &ContainerNode<S>,
+        _handle: &DomHandle,
+    ) -> VisitFlow {
+        VisitFlow::Continue
+    }
+
+    /// Called once all of a container's children have been visited.
+    fn visit_container_exit(
+        &mut self,
+        _node: &ContainerNode<S>,
+        _handle: &DomHandle,
+    ) -> VisitFlow {
+        VisitFlow::Continue
+    }
+
+    /// Called for each text node.
+    fn visit_text(&mut self, _node: &TextNode<S>, _handle: &DomHandle) -> VisitFlow {
+        VisitFlow::Continue
+    }
+}
+
+/// Directive returned from a [DomVisitorMut] callback describing what the
+/// driver should do with the node it was just given, in addition to the
+/// plain [VisitFlow] control flow.
+pub enum MutateFlow<S>
+where
+    S: UnicodeString,
+{
+    /// Keep walking, making no change to the visited node.
+    Continue,
+    /// Don't descend into the container just entered.
+    SkipChildren,
+    /// Abort the walk immediately.
+    Stop,
+    /// Replace the visited node with a different one. The replacement is
+    /// not itself walked.
+    Replace(DomNode<S>),
+    /// Remove the visited node. The driver re-merges adjacent text nodes
+    /// and drops any container left empty by the removal, so this module's
+    /// invariants hold without the visitor having to maintain them.
+    Remove,
+}
+
+/// A mutating visitor over a [Dom], driven by [Dom::accept_mut]. Lets
+/// transformation passes (formatting normalization, mention rewriting, link
+/// detection, ...) be written as composable visitors instead of collecting
+/// handles and mutating the `Dom` separately.
+///
+/// Note: [DomNode::LineBreak] and [DomNode::Zwsp] nodes are walked but have
+/// no dedicated callback, so a visitor currently can't observe, replace or
+/// remove them. A pass that needs to rewrite line breaks or ZWSPs has to
+/// fall back to collecting handles via [Dom::iter] instead.
+pub trait DomVisitorMut<S>
+where
+    S: UnicodeString,
+{
+    /// Called when descending into a container, before any of its children
+    /// are visited.
+    fn visit_container_enter(
+        &mut self,
+        _node: &mut ContainerNode<S>,
+        _handle: &DomHandle,
+    ) -> MutateFlow<S> {
+        MutateFlow::Continue
+    }
+
+    /// Called once all of a container's children have been visited.
+    fn visit_container_exit(
+        &mut self,
+        _node: &mut ContainerNode<S>,
+        _handle: &DomHandle,
+    ) -> MutateFlow<S> {
+        MutateFlow::Continue
+    }
+
+    /// Called for each text node.
+    fn visit_text(
+        &mut self,
+        _node: &mut TextNode<S>,
+        _handle: &DomHandle,
+    ) -> MutateFlow<S> {
+        MutateFlow::Continue
+    }
+}
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Walk the DOM depth-first, invoking `visitor`'s callbacks. Stops as
+    /// soon as the visitor returns [VisitFlow::Stop].
+    pub fn accept(&self, visitor: &mut impl DomVisitor<S>) {
+        accept_node(self.document_node(), visitor);
+    }
+
+    /// Walk the DOM depth-first, invoking `visitor`'s callbacks and applying
+    /// any [MutateFlow::Replace] / [MutateFlow::Remove] directive it
+    /// returns. Stops as soon as the visitor returns [MutateFlow::Stop].
+    pub fn accept_mut(&mut self, visitor: &mut impl DomVisitorMut<S>) {
+        accept_node_mut(self.document_mut(), visitor);
+    }
+}
+
+/// Returns `false` if the visitor asked the walk to stop.
+fn accept_node<S>(node: &DomNode<S>, visitor: &mut impl DomVisitor<S>) -> bool
+where
+    S: UnicodeString,
+{
+    match node {
+        DomNode::Container(c) => {
+            match visitor.visit_container_enter(c, &c.handle()) {
+                VisitFlow::Stop => return false,
+                VisitFlow::SkipChildren => return true,
+                VisitFlow::Continue => {}
+            }
+            for child in c.children() {
+                if !accept_node(child, visitor) {
+                    return false;
+                }
+            }
+            visitor.visit_container_exit(c, &c.handle()) != VisitFlow::Stop
+        }
+        DomNode::Text(t) => visitor.visit_text(t, &t.handle()) != VisitFlow::Stop,
+        // No visitor callback for these yet - see the note on DomVisitor.
+        DomNode::LineBreak(_) | DomNode::Zwsp(_) => true,
+    }
+}
+
+/// Walks `node` and its descendants, applying `visitor`'s directives as it
+/// goes. The single [MutateFlow] return value does double duty: it tells
+/// the caller (a parent container, or [Dom::accept_mut] at the root) both
+/// whether to abort the whole walk ([MutateFlow::Stop]) and whether to drop
+/// `node` from its parent ([MutateFlow::Remove]) - there's no separate
+/// out-parameter, so the caller tells the two apart by matching on the
+/// variant it gets back.
+fn accept_node_mut<S>(
+    node: &mut DomNode<S>,
+    visitor: &mut impl DomVisitorMut<S>,
+) -> MutateFlow<S>
+where
+    S: UnicodeString,
+{
+    match node {
+        DomNode::Container(c) => {
+            let handle = c.handle();
+            match visitor.visit_container_enter(c, &handle) {
+                MutateFlow::Stop => return MutateFlow::Stop,
+                MutateFlow::Remove => return MutateFlow::Remove,
+                MutateFlow::Replace(replacement) => {
+                    *node = replacement;
+                    return MutateFlow::Continue;
+                }
+                MutateFlow::SkipChildren => return MutateFlow::Continue,
+                MutateFlow::Continue => {}
+            }
+
+            let DomNode::Container(c) = node else {
+                unreachable!("node is still the container we just matched on")
+            };
+            let mut idx = 0;
+            while idx < c.children_mut().len() {
+                match accept_node_mut(&mut c.children_mut()[idx], visitor) {
+                    MutateFlow::Stop => return MutateFlow::Stop,
+                    MutateFlow::Remove => {
+                        c.children_mut().remove(idx);
+                    }
+                    _ => idx += 1,
+                }
+            }
+            merge_adjacent_text_children(c);
+
+            let handle = c.handle();
+            match visitor.visit_container_exit(c, &handle) {
+                MutateFlow::Replace(replacement) => {
+                    *node = replacement;
+                    MutateFlow::Continue
+                }
+                MutateFlow::Stop => MutateFlow::Stop,
+                MutateFlow::Remove => MutateFlow::Remove,
+                _ if c.children().is_empty() => MutateFlow::Remove,
+                _ => MutateFlow::Continue,
+            }
+        }
+        DomNode::Text(t) => {
+            let handle = t.handle();
+            match visitor.visit_text(t, &handle) {
+                MutateFlow::Replace(replacement) => {
+                    *node = replacement;
+                    MutateFlow::Continue
+                }
+                other => other,
+            }
+        }
+        // No visitor callback for these yet - see the note on DomVisitorMut.
+        DomNode::LineBreak(_) | DomNode::Zwsp(_) => MutateFlow::Continue,
+    }
+}
+
+/// Re-establish the "no adjacent text nodes" invariant among `container`'s
+/// direct children after a visitor may have replaced or removed some of
+/// them.
+fn merge_adjacent_text_children<S>(container: &mut ContainerNode<S>)
+where
+    S: UnicodeString,
+{
+    let mut idx = 0;
+    while idx + 1 < container.children_mut().len() {
+        let merged = match &container.children()[idx..idx + 2] {
+            [DomNode::Text(a), DomNode::Text(b)] => {
+                Some(TextNode::from(a.data().clone() + b.data()))
+            }
+            _ => None,
+        };
+        if let Some(merged) = merged {
+            container.children_mut()[idx] = DomNode::Text(merged);
+            container.children_mut().remove(idx + 1);
+        } else {
+            idx += 1;
+        }
+    }
+}
+
+/// How a [SelectorSegment] relates to the segment to its left.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+enum Combinator {
+    /// `a b` - `b` may be any descendant of `a`.
+    Descendant,
+    /// `a > b` - `b` must be a direct child of `a`.
+    Child,
+}
+
+/// A predicate a [SelectorSegment] must also satisfy, beyond its tag name.
+enum Predicate {
+    /// `:contains(text)` - the node's text content contains `text`.
+    Contains(String),
+    /// `[name]` / `[name=value]` - the node carries the attribute `name`,
+    /// optionally with a specific value (e.g. a link's `href`).
+    Attr(String, Option<String>),
+}
+
+struct SelectorSegment {
+    combinator: Combinator,
+    tag: Option<String>,
+    predicates: Vec<Predicate>,
+}
+
+/// A compact, CSS-like selector over a [Dom]'s node model, built with
+/// [Selector::parse] and run with [Dom::select]. Supports tag names (`strong`, `ul`, `li`), descendant
+/// (`ul li`) and child (`ul > li`) combinators, and `:contains(text)` /
+/// `[attr]` predicates.
+pub struct Selector {
+    // Segments are stored left-to-right as written (`ul > li` is
+    // `[ul, li]`), but matched right-to-left against a candidate node.
+    segments: Vec<SelectorSegment>,
+}
+
+/// Error returned by [Selector::parse] for syntax this selector dialect
+/// doesn't support.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SelectorParseError(String);
+
+impl Selector {
+    /// Parse a selector such as `"ul > li"`, `"a[href]"` or
+    /// `"li:contains(todo)"`.
+    pub fn parse(input: &str) -> Result<Self, SelectorParseError> {
+        let mut segments = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        for token in input.split_whitespace() {
+            if token == ">" {
+                combinator = Combinator::Child;
+                continue;
+            }
+            segments.push(parse_segment(token, combinator)?);
+            combinator = Combinator::Descendant;
+        }
+        if segments.is_empty() {
+            return Err(SelectorParseError(format!(
+                "empty selector: `{input}`"
+            )));
+        }
+        Ok(Self { segments })
+    }
+
+    fn matches<S>(&self, dom: &Dom<S>, node: &DomNode<S>) -> bool
+    where
+        S: UnicodeString,
+    {
+        let Some((last, rest)) = self.segments.split_last() else {
+            return false;
+        };
+        if !segment_matches(last, dom, node) {
+            return false;
+        }
+
+        let mut handle = node.handle();
+        // `segment.combinator` records how a segment relates to its *own*
+        // left neighbor (e.g. for `"ul > li"`, it's `li` that carries
+        // `Child`, not `ul`). So as we walk right-to-left, the combinator
+        // that governs matching the *next* segment is the one carried by
+        // the segment we just matched, not the one we're about to match.
+        let mut combinator = last.combinator;
+        for segment in rest.iter().rev() {
+            match combinator {
+                Combinator::Child => {
+                    if !handle.has_parent() {
+                        return false;
+                    }
+                    handle = handle.parent_handle();
+                    if !segment_matches(segment, dom, dom.lookup_node(&handle)) {
+                        return false;
+                    }
+                }
+                Combinator::Descendant => {
+                    let mut found = false;
+                    while handle.has_parent() {
+                        handle = handle.parent_handle();
+                        if segment_matches(segment, dom, dom.lookup_node(&handle)) {
+                            found = true;
+                            break;
+                        }
+                    }
+                    if !found {
+                        return false;
+                    }
+                }
+            }
+            combinator = segment.combinator;
+        }
+        true
+    }
+}
+
+fn parse_segment(
+    token: &str,
+    combinator: Combinator,
+) -> Result<SelectorSegment, SelectorParseError> {
+    let mut predicates = Vec::new();
+
+    // Split the leading tag name (possibly empty, e.g. `[href]`) off from
+    // any trailing `:contains(...)` / `[...]` predicates.
+    let tag_end = token.find(['[', ':']).unwrap_or(token.len());
+    let tag = &token[..tag_end];
+    let mut remaining = &token[tag_end..];
+
+    while !remaining.is_empty() {
+        if let Some(inner) = remaining.strip_prefix(":contains(") {
+            let Some(end) = inner.find(')') else {
+                return Err(SelectorParseError(format!(
+                    "unterminated :contains(...) in `{token}`"
+                )));
+            };
+            predicates.push(Predicate::Contains(inner[..end].to_string()));
+            remaining = &inner[end + 1..];
+        } else if let Some(inner) = remaining.strip_prefix('[') {
+            let Some(end) = inner.find(']') else {
+                return Err(SelectorParseError(format!(
+                    "unterminated [...] in `{token}`"
+                )));
+            };
+            let attr = &inner[..end];
+            predicates.push(match attr.split_once('=') {
+                Some((name, value)) => {
+                    Predicate::Attr(name.to_string(), Some(value.to_string()))
+                }
+                None => Predicate::Attr(attr.to_string(), None),
+            });
+            remaining = &inner[end + 1..];
+        } else {
+            return Err(SelectorParseError(format!(
+                "unexpected `{remaining}` in `{token}`"
+            )));
+        }
+    }
+
+    Ok(SelectorSegment {
+        combinator,
+        tag: if tag.is_empty() {
+            None
+        } else {
+            Some(tag.to_string())
+        },
+        predicates,
+    })
+}
+
+fn segment_matches<S>(
+    segment: &SelectorSegment,
+    dom: &Dom<S>,
+    node: &DomNode<S>,
+) -> bool
+where
+    S: UnicodeString,
+{
+    if let Some(tag) = &segment.tag {
+        let DomNode::Container(c) = node else {
+            return false;
+        };
+        if c.name().to_string() != *tag {
+            return false;
+        }
+    }
+    segment
+        .predicates
+        .iter()
+        .all(|predicate| predicate_matches(predicate, dom, node))
+}
+
+fn predicate_matches<S>(
+    predicate: &Predicate,
+    _dom: &Dom<S>,
+    node: &DomNode<S>,
+) -> bool
+where
+    S: UnicodeString,
+{
+    match predicate {
+        Predicate::Contains(needle) => node
+            .iter_subtree()
+            .filter_map(DomNode::as_text)
+            .any(|text| text.data().to_string().contains(needle.as_str())),
+        Predicate::Attr(name, value) => {
+            let DomNode::Container(c) = node else {
+                return false;
+            };
+            match c.get_attribute(name) {
+                Some(actual) => value
+                    .as_ref()
+                    .map(|expected| actual == expected.as_str())
+                    .unwrap_or(true),
+                None => false,
+            }
+        }
+    }
+}
+
+impl<S> Dom<S>
+where
+    S: UnicodeString,
+{
+    /// Return the handles of every node in this DOM matching `selector`, in
+    /// depth-first order. Returning handles rather than references keeps
+    /// results usable across later mutations of the `Dom`.
+    pub fn select<'a>(
+        &'a self,
+        selector: &'a Selector,
+    ) -> impl Iterator<Item = DomHandle> + 'a {
+        self.iter()
+            .filter(move |node| selector.matches(self, node))
+            .map(DomNode::handle)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use widestring::Utf16String;
@@ -337,6 +1134,11 @@ mod test {
     use crate::tests::testutils_composer_model::cm;
     use crate::{DomHandle, DomNode};
 
+    use super::{
+        Descend, DomEvent, DomVisitor, DomVisitorMut, MutateFlow, Selector,
+        VisitFlow,
+    };
+
     const EXAMPLE_HTML: &str = "\
         <ul>\
             <li>b<strong>c</strong></li>\
@@ -617,6 +1419,436 @@ mod test {
         );
     }
 
+    #[test]
+    fn can_walk_events_of_all_nodes() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let events: Vec<String> = dom.iter_events().map(event_txt).collect();
+
+        assert_eq!(
+            events,
+            vec![
+                "enter:",
+                "enter:ul",
+                "enter:li",
+                "leaf:'b'",
+                "enter:strong",
+                "leaf:'c'",
+                "exit:strong",
+                "exit:li",
+                "enter:li",
+                "leaf:'foo'",
+                "exit:li",
+                "exit:ul",
+                "enter:i",
+                "leaf:'d'",
+                "exit:i",
+                "leaf:'e'",
+                "leaf:br",
+                "enter:b",
+                "leaf:'x'",
+                "exit:b",
+                "exit:",
+            ]
+        );
+    }
+
+    #[test]
+    fn can_walk_events_of_a_subtree() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let first_child = dom.children().first().unwrap();
+        let events: Vec<String> =
+            first_child.iter_subtree_events().map(event_txt).collect();
+
+        assert_eq!(
+            events,
+            vec![
+                "enter:ul",
+                "enter:li",
+                "leaf:'b'",
+                "enter:strong",
+                "leaf:'c'",
+                "exit:strong",
+                "exit:li",
+                "enter:li",
+                "leaf:'foo'",
+                "exit:li",
+                "exit:ul",
+            ]
+        );
+    }
+
+    #[test]
+    fn can_walk_events_of_a_subtree_reversed() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let first_child = dom.children().first().unwrap();
+        let events: Vec<String> = first_child
+            .iter_subtree_events()
+            .rev()
+            .map(event_txt)
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                "exit:ul",
+                "exit:li",
+                "leaf:'foo'",
+                "enter:li",
+                "exit:li",
+                "exit:strong",
+                "leaf:'c'",
+                "enter:strong",
+                "leaf:'b'",
+                "enter:li",
+                "enter:ul",
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_visits_every_node_in_depth_first_order() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+
+        struct Collector(Vec<String>);
+        impl DomVisitor<Utf16String> for Collector {
+            fn visit_container_enter(
+                &mut self,
+                node: &crate::dom::nodes::ContainerNode<Utf16String>,
+                _handle: &DomHandle,
+            ) -> VisitFlow {
+                self.0.push(node.name().to_string());
+                VisitFlow::Continue
+            }
+
+            fn visit_text(
+                &mut self,
+                node: &crate::dom::nodes::TextNode<Utf16String>,
+                _handle: &DomHandle,
+            ) -> VisitFlow {
+                self.0.push(node.data().to_string());
+                VisitFlow::Continue
+            }
+        }
+
+        let mut collector = Collector(Vec::new());
+        dom.accept(&mut collector);
+
+        assert_eq!(
+            collector.0,
+            vec![
+                "", "ul", "li", "b", "strong", "c", "li", "foo", "i", "d",
+                "e", "b", "x"
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_skip_children_prunes_the_subtree() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+
+        struct SkipLists(Vec<String>);
+        impl DomVisitor<Utf16String> for SkipLists {
+            fn visit_container_enter(
+                &mut self,
+                node: &crate::dom::nodes::ContainerNode<Utf16String>,
+                _handle: &DomHandle,
+            ) -> VisitFlow {
+                self.0.push(node.name().to_string());
+                if node.name() == "ul" {
+                    VisitFlow::SkipChildren
+                } else {
+                    VisitFlow::Continue
+                }
+            }
+
+            fn visit_text(
+                &mut self,
+                node: &crate::dom::nodes::TextNode<Utf16String>,
+                _handle: &DomHandle,
+            ) -> VisitFlow {
+                self.0.push(node.data().to_string());
+                VisitFlow::Continue
+            }
+        }
+
+        let mut visitor = SkipLists(Vec::new());
+        dom.accept(&mut visitor);
+
+        assert_eq!(
+            visitor.0,
+            vec!["", "ul", "i", "d", "e", "b", "x"]
+        );
+    }
+
+    #[test]
+    fn accept_mut_replace_swaps_the_visited_node() {
+        let mut dom = cm(EXAMPLE_HTML).state.dom;
+
+        struct ReplaceStrong;
+        impl DomVisitorMut<Utf16String> for ReplaceStrong {
+            fn visit_container_enter(
+                &mut self,
+                node: &mut crate::dom::nodes::ContainerNode<Utf16String>,
+                _handle: &DomHandle,
+            ) -> MutateFlow<Utf16String> {
+                if node.name() == "strong" {
+                    MutateFlow::Replace(DomNode::Text(
+                        crate::dom::nodes::TextNode::from("C".into()),
+                    ))
+                } else {
+                    MutateFlow::Continue
+                }
+            }
+        }
+
+        dom.accept_mut(&mut ReplaceStrong);
+
+        let text_nodes: Vec<String> = dom.iter().map(node_txt).collect();
+        assert_eq!(
+            text_nodes,
+            vec![
+                "", "ul", "li", "'bC'", "li", "'foo'", "i", "'d'", "'e'",
+                "br", "b", "'x'"
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_mut_remove_drops_the_visited_node() {
+        let mut dom = cm(EXAMPLE_HTML).state.dom;
+
+        struct RemoveSecondListItem;
+        impl DomVisitorMut<Utf16String> for RemoveSecondListItem {
+            fn visit_container_enter(
+                &mut self,
+                _node: &mut crate::dom::nodes::ContainerNode<Utf16String>,
+                handle: &DomHandle,
+            ) -> MutateFlow<Utf16String> {
+                if *handle == DomHandle::from_raw(vec![0, 1]) {
+                    MutateFlow::Remove
+                } else {
+                    MutateFlow::Continue
+                }
+            }
+        }
+
+        dom.accept_mut(&mut RemoveSecondListItem);
+
+        let text_nodes: Vec<String> = dom.iter().map(node_txt).collect();
+        assert_eq!(
+            text_nodes,
+            vec![
+                "", "ul", "li", "'b'", "strong", "'c'", "i", "'d'", "'e'",
+                "br", "b", "'x'"
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_mut_remove_drops_containers_left_empty() {
+        let mut dom = cm(EXAMPLE_HTML).state.dom;
+
+        struct RemoveTextD;
+        impl DomVisitorMut<Utf16String> for RemoveTextD {
+            fn visit_text(
+                &mut self,
+                node: &mut crate::dom::nodes::TextNode<Utf16String>,
+                _handle: &DomHandle,
+            ) -> MutateFlow<Utf16String> {
+                if node.data().to_string() == "d" {
+                    MutateFlow::Remove
+                } else {
+                    MutateFlow::Continue
+                }
+            }
+        }
+
+        dom.accept_mut(&mut RemoveTextD);
+
+        // Removing the only child of <i>d</i> leaves it empty, so the
+        // driver must drop <i> itself rather than yield an empty container.
+        let text_nodes: Vec<String> = dom.iter().map(node_txt).collect();
+        assert_eq!(
+            text_nodes,
+            vec![
+                "", "ul", "li", "'b'", "strong", "'c'", "li", "'foo'", "'e'",
+                "br", "b", "'x'"
+            ]
+        );
+    }
+
+    #[test]
+    fn accept_mut_stop_aborts_the_walk_immediately() {
+        let mut dom = cm(EXAMPLE_HTML).state.dom;
+
+        struct StopAtFoo(Vec<String>);
+        impl DomVisitorMut<Utf16String> for StopAtFoo {
+            fn visit_text(
+                &mut self,
+                node: &mut crate::dom::nodes::TextNode<Utf16String>,
+                _handle: &DomHandle,
+            ) -> MutateFlow<Utf16String> {
+                let data = node.data().to_string();
+                self.0.push(data.clone());
+                if data == "foo" {
+                    MutateFlow::Stop
+                } else {
+                    MutateFlow::Continue
+                }
+            }
+        }
+
+        let mut visitor = StopAtFoo(Vec::new());
+        dom.accept_mut(&mut visitor);
+
+        assert_eq!(visitor.0, vec!["b", "c", "foo"]);
+        // Nothing was replaced or removed, so the dom itself is untouched.
+        let text_nodes: Vec<String> = dom.iter().map(node_txt).collect();
+        assert_eq!(
+            text_nodes,
+            vec![
+                "", "ul", "li", "'b'", "strong", "'c'", "li", "'foo'", "i",
+                "'d'", "'e'", "br", "b", "'x'"
+            ]
+        );
+    }
+
+    #[test]
+    fn select_by_tag_name_finds_every_match() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let selector = Selector::parse("li").unwrap();
+
+        assert_eq!(dom.select(&selector).count(), 2);
+    }
+
+    #[test]
+    fn select_by_descendant_combinator_finds_nested_matches() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let selector = Selector::parse("ul li").unwrap();
+
+        assert_eq!(dom.select(&selector).count(), 2);
+    }
+
+    #[test]
+    fn select_by_child_combinator_only_finds_direct_children() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+
+        assert_eq!(
+            dom.select(&Selector::parse("ul > li").unwrap()).count(),
+            2
+        );
+        assert_eq!(
+            dom.select(&Selector::parse("ul > strong").unwrap()).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn select_with_contains_predicate_filters_by_text() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let selector = Selector::parse("li:contains(foo)").unwrap();
+        let matches: Vec<DomHandle> = dom.select(&selector).collect();
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0], DomHandle::from_raw(vec![0, 1]));
+    }
+
+    #[test]
+    fn iter_where_prunes_skipped_subtrees_but_still_yields_them() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let text_nodes: Vec<String> = dom
+            .iter_where(|node| match node {
+                DomNode::Container(c)
+                    if ["strong", "i", "b"].contains(&c.name().to_string().as_str()) =>
+                {
+                    Descend::Skip
+                }
+                _ => Descend::Into,
+            })
+            .map(node_txt)
+            .collect();
+
+        assert_eq!(
+            text_nodes,
+            vec![
+                "", "ul", "li", "'b'", "strong", "li", "'foo'", "i", "'e'",
+                "br", "b"
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_where_with_a_predicate_that_always_descends_matches_iter() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+
+        let plain: Vec<String> = dom.iter().map(node_txt).collect();
+        let pruning: Vec<String> = dom
+            .iter_where(|_| Descend::Into)
+            .map(node_txt)
+            .collect();
+
+        assert_eq!(pruning, plain);
+    }
+
+    #[test]
+    fn iter_subtree_where_prunes_skipped_subtrees_but_still_yields_them() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let first_child = dom.children().first().unwrap();
+
+        let text_nodes: Vec<String> = first_child
+            .iter_subtree_where(|node| match node {
+                DomNode::Container(c) if c.name() == "strong" => {
+                    Descend::Skip
+                }
+                _ => Descend::Into,
+            })
+            .map(node_txt)
+            .collect();
+
+        assert_eq!(
+            text_nodes,
+            vec!["ul", "li", "'b'", "strong", "li", "'foo'"]
+        );
+    }
+
+    #[test]
+    fn event_iterator_supports_interleaved_next_and_next_back() {
+        let dom = cm(EXAMPLE_HTML).state.dom;
+        let first_child = dom.children().first().unwrap();
+
+        let expected: Vec<String> =
+            first_child.iter_subtree_events().map(event_txt).collect();
+
+        let mut iter = first_child.iter_subtree_events();
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        loop {
+            let from_front = iter.next();
+            if let Some(event) = from_front {
+                front.push(event_txt(event));
+            }
+            let from_back = iter.next_back();
+            if let Some(event) = from_back {
+                back.push(event_txt(event));
+            }
+            if from_front.is_none() && from_back.is_none() {
+                break;
+            }
+        }
+        back.reverse();
+        front.extend(back);
+
+        assert_eq!(front, expected);
+    }
+
+    fn event_txt(event: DomEvent<Utf16String>) -> String {
+        match event {
+            DomEvent::Enter(node) => format!("enter:{}", node_txt(node)),
+            DomEvent::Exit(node) => format!("exit:{}", node_txt(node)),
+            DomEvent::Leaf(node) => format!("leaf:{}", node_txt(node)),
+        }
+    }
+
     fn node_txt(node: &DomNode<Utf16String>) -> String {
         match node {
             DomNode::Container(c) => c.name().to_string(),